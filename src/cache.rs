@@ -0,0 +1,150 @@
+///! In-memory response cache
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+    time::Instant,
+};
+
+/// A single cached response, keyed by `(method, url)` in [`Cache`]
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// Status line and headers, terminated by the blank line
+    pub head: String,
+    /// Response body bytes
+    pub body: Vec<u8>,
+    /// When this entry stops being fresh; entries are only stored when this is `Some`
+    /// (a `max-age` was present), since there is no revalidation path for `no-cache`
+    pub expires_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct CacheState {
+    map: HashMap<(String, String), CacheEntry>,
+    recency: VecDeque<(String, String)>,
+}
+
+/// Bounded LRU cache of proxied responses, guarded by a [`Mutex`] so the [`ThreadPool`](crate::thread_pool::ThreadPool)
+/// can share it safely across connections
+pub struct Cache {
+    max_entries: usize,
+    state: Mutex<CacheState>,
+}
+
+impl fmt::Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+impl Cache {
+    /// Create a cache bounded to `max_entries`, evicting the least-recently-used entry on insert
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            state: Mutex::new(CacheState {
+                map: HashMap::new(),
+                recency: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Look up a cached entry by method and url, returning a clone when it is still
+    /// fresh; expired entries are evicted on the way out
+    pub fn get(&self, method: &str, url: &str) -> Option<CacheEntry> {
+        let key = (method.to_string(), url.to_string());
+        let mut state = self.state.lock().unwrap();
+        let entry = state.map.get(&key)?.clone();
+
+        if let Some(expires_at) = entry.expires_at {
+            if Instant::now() >= expires_at {
+                state.map.remove(&key);
+                state.recency.retain(|k| k != &key);
+                return None;
+            }
+        }
+
+        state.recency.retain(|k| k != &key);
+        state.recency.push_back(key);
+        Some(entry)
+    }
+
+    /// Insert or replace a response, evicting the least-recently-used entry when the
+    /// cache is full
+    pub fn put(&self, method: &str, url: &str, entry: CacheEntry) {
+        let key = (method.to_string(), url.to_string());
+        let mut state = self.state.lock().unwrap();
+
+        if !state.map.contains_key(&key) && state.map.len() >= self.max_entries {
+            if let Some(oldest) = state.recency.pop_front() {
+                state.map.remove(&oldest);
+            }
+        }
+
+        state.recency.retain(|k| k != &key);
+        state.recency.push_back(key.clone());
+        state.map.insert(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(body: &[u8]) -> CacheEntry {
+        CacheEntry {
+            head: "HTTP/1.1 200 OK\r\n\r\n".to_string(),
+            body: body.to_vec(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_when_full() {
+        let cache = Cache::new(2);
+        cache.put("GET", "/a", entry(b"a"));
+        cache.put("GET", "/b", entry(b"b"));
+        cache.put("GET", "/c", entry(b"c"));
+
+        assert!(cache.get("GET", "/a").is_none());
+        assert!(cache.get("GET", "/b").is_some());
+        assert!(cache.get("GET", "/c").is_some());
+    }
+
+    #[test]
+    fn get_refreshes_recency_so_it_is_not_the_next_eviction() {
+        let cache = Cache::new(2);
+        cache.put("GET", "/a", entry(b"a"));
+        cache.put("GET", "/b", entry(b"b"));
+
+        assert!(cache.get("GET", "/a").is_some());
+
+        cache.put("GET", "/c", entry(b"c"));
+
+        assert!(cache.get("GET", "/b").is_none());
+        assert!(cache.get("GET", "/a").is_some());
+        assert!(cache.get("GET", "/c").is_some());
+    }
+
+    #[test]
+    fn expired_entry_is_not_returned_and_is_evicted() {
+        let cache = Cache::new(2);
+        cache.put(
+            "GET",
+            "/a",
+            CacheEntry {
+                head: "HTTP/1.1 200 OK\r\n\r\n".to_string(),
+                body: b"a".to_vec(),
+                expires_at: Some(Instant::now() - Duration::from_secs(1)),
+            },
+        );
+
+        assert!(cache.get("GET", "/a").is_none());
+
+        let mut state = cache.state.lock().unwrap();
+        assert!(!state.map.contains_key(&("GET".to_string(), "/a".to_string())));
+    }
+}
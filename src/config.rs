@@ -0,0 +1,134 @@
+///! TOML-based proxy configuration
+use crate::{log::LogLevel, Builder};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+/// Shape of the proxy's `*.toml` configuration file
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    address: Option<String>,
+    target: Option<String>,
+    threads: Option<usize>,
+    log_level: Option<String>,
+    #[serde(default)]
+    route: Vec<RouteConfig>,
+}
+
+/// A single `[[route]]` table mapping a path prefix to an upstream target
+#[derive(Debug, Deserialize)]
+struct RouteConfig {
+    path: String,
+    target: String,
+}
+
+fn parse_log_level(raw: &str) -> Result<LogLevel> {
+    match raw.to_lowercase().as_str() {
+        "error" => Ok(LogLevel::Error),
+        "warn" => Ok(LogLevel::Warn),
+        "info" => Ok(LogLevel::Info),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown log level: {}", raw),
+        )),
+    }
+}
+
+impl Builder {
+    /// Load proxy settings (listen address, upstream target, thread count, log level
+    /// and path→upstream routes) from a `*.toml` configuration file, so operators can
+    /// reconfigure routing and threading without recompiling
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self> {
+        let raw = fs::read_to_string(path)?;
+        let file: ConfigFile =
+            toml::from_str(&raw).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+        let mut builder = Builder::new();
+        if let Some(address) = file.address {
+            builder = builder.with_address(address);
+        }
+        if let Some(target) = file.target {
+            builder = builder.with_target(target);
+        }
+        if let Some(threads) = file.threads {
+            builder = builder.with_threads(threads);
+        }
+        if let Some(log_level) = file.log_level {
+            builder = builder.with_log_level(parse_log_level(&log_level)?);
+        }
+        for route in file.route {
+            builder = builder.with_route(route.path, route.target);
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    /// Write `contents` to a fresh temp file and return its path, so each test gets
+    /// its own file without pulling in a tempfile dependency this crate doesn't have
+    fn write_temp_toml(contents: &str) -> std::path::PathBuf {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("proxy_server_config_test_{}_{}.toml", std::process::id(), n));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn unset_fields_keep_builder_defaults() {
+        let path = write_temp_toml("threads = 8\n");
+        let builder = Builder::from_toml_file(&path).unwrap();
+        let defaults = Builder::new();
+
+        assert_eq!(builder.threads, 8);
+        assert_eq!(builder.address, defaults.address);
+        assert_eq!(builder.target, defaults.target);
+        assert_eq!(format!("{:?}", builder.log_level), format!("{:?}", defaults.log_level));
+        assert!(builder.routes.is_empty());
+    }
+
+    #[test]
+    fn parses_routes() {
+        let path = write_temp_toml(
+            r#"
+            [[route]]
+            path = "/api"
+            target = "127.0.0.1:4001"
+
+            [[route]]
+            path = "/static"
+            target = "127.0.0.1:4002"
+            "#,
+        );
+        let builder = Builder::from_toml_file(&path).unwrap();
+
+        assert_eq!(
+            builder.routes,
+            vec![
+                ("/api".to_string(), "127.0.0.1:4001".to_string()),
+                ("/static".to_string(), "127.0.0.1:4002".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_toml() {
+        let path = write_temp_toml("this is not [ valid toml");
+        assert!(Builder::from_toml_file(&path).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_log_level() {
+        let path = write_temp_toml("log_level = \"verbose\"\n");
+        assert!(Builder::from_toml_file(&path).is_err());
+    }
+}
@@ -7,9 +7,32 @@ use serde::Serialize;
 use std::{
     fmt,
     io::{Error, ErrorKind, Result},
+    net::IpAddr,
     str,
 };
 
+/// Hop-by-hop headers per RFC 2616 §13.5.1 that a proxy must strip, since they describe
+/// the connection to its immediate neighbour rather than the end-to-end request/response
+const HOP_BY_HOP: [&str; 8] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailers",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// Cache-relevant `Cache-Control` directives, parsed by [`Headers::get_cache_control`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub private: bool,
+    pub max_age: Option<u64>,
+}
+
 #[cfg_attr(feature = "napi", napi(object))]
 #[derive(Debug, Serialize, Clone)]
 pub struct Header {
@@ -147,6 +170,19 @@ impl Headers {
         url.to_string()
     }
 
+    /// Get the full request target (path plus query string) from raw headers, as it
+    /// appears on the request line. Unlike [`Headers::get_url`] (which is deliberately
+    /// path-only, for route prefix matching), this preserves the query string so callers
+    /// that need to distinguish e.g. `/search?q=foo` from `/search?q=bar` can do so.
+    pub fn get_request_target(raw: &String) -> String {
+        let reg = Regex::new(r"(?m)^\w+ (\S+) HTTP").unwrap();
+        let capts = reg.captures(raw.as_str());
+        match capts {
+            Some(capts) => capts.get(1).unwrap().as_str().to_string(),
+            None => "/".to_string(),
+        }
+    }
+
     // Get protocol from raw headers
     pub fn get_protocol(raw: &String) -> String {
         let reg = Regex::new(r"HTTPS?\/\d+\.\d+").unwrap();
@@ -185,4 +221,175 @@ impl Headers {
         let method = capts.get(0).unwrap().as_str();
         method.to_string()
     }
+
+    /// The header name on a raw header line (the part before its first `:`), or `None`
+    /// for a line with no `:` at all (such as an origin-form request line, the status
+    /// line, the trailing blank line)
+    fn line_header_name(line: &str) -> Option<&str> {
+        line.split_once(':').map(|(name, _)| name.trim())
+    }
+
+    /// Strip hop-by-hop headers (RFC 2616 §13.5.1) plus any header named in the
+    /// incoming `Connection:` value, so connection-scoped state is never forwarded
+    /// to the other side of the proxy. Matching is done per CRLF-delimited line
+    /// against the header name only, so e.g. stripping `Connection` can't also eat
+    /// into an unrelated `Proxy-Connection` line.
+    pub fn strip_hop_by_hop(&mut self) {
+        let mut names: Vec<String> = HOP_BY_HOP.iter().map(|n| n.to_string()).collect();
+
+        let connection_line = self.raw.split("\r\n").find(|line| {
+            Headers::line_header_name(line)
+                .map(|name| name.eq_ignore_ascii_case("Connection"))
+                .unwrap_or(false)
+        });
+        if let Some(line) = connection_line {
+            if let Some((_, value)) = line.split_once(':') {
+                for part in value.split(',') {
+                    let name = part.trim();
+                    if !name.is_empty() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        let kept: Vec<&str> = self
+            .raw
+            .split("\r\n")
+            .filter(|line| match Headers::line_header_name(line) {
+                Some(name) => !names.iter().any(|n| n.eq_ignore_ascii_case(name)),
+                None => true,
+            })
+            .collect();
+        self.raw = kept.join("\r\n");
+
+        self.list
+            .retain(|h| !names.iter().any(|n| n.eq_ignore_ascii_case(&h.name)));
+    }
+
+    /// Append the client's peer address to the `X-Forwarded-For` header, creating it
+    /// right after the request/status line when absent
+    pub fn append_forwarded_for(&mut self, ip: IpAddr) {
+        let mut found = false;
+        let mut lines: Vec<String> = self
+            .raw
+            .split("\r\n")
+            .map(|line| match Headers::line_header_name(line) {
+                Some(name) if name.eq_ignore_ascii_case("X-Forwarded-For") => {
+                    found = true;
+                    let value = line.split_once(':').map(|(_, v)| v.trim()).unwrap_or("");
+                    format!("X-Forwarded-For: {}, {}", value, ip)
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+
+        if !found && !lines.is_empty() {
+            lines.insert(1, format!("X-Forwarded-For: {}", ip));
+        }
+        self.raw = lines.join("\r\n");
+    }
+
+    /// Whether the headers declare `Transfer-Encoding: chunked`
+    pub fn is_chunked(raw: &String) -> bool {
+        Regex::new(r"(?i)transfer-encoding:\s*chunked")
+            .unwrap()
+            .is_match(raw.as_str())
+    }
+
+    /// Set the `Content-Length` header to `len`, replacing it if already present or
+    /// inserting it right after the status/request line otherwise
+    pub fn set_content_length(&mut self, len: usize) {
+        let mut found = false;
+        let mut lines: Vec<String> = self
+            .raw
+            .split("\r\n")
+            .map(|line| match Headers::line_header_name(line) {
+                Some(name) if name.eq_ignore_ascii_case("Content-Length") => {
+                    found = true;
+                    format!("Content-Length: {}", len)
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+
+        if !found && !lines.is_empty() {
+            lines.insert(1, format!("Content-Length: {}", len));
+        }
+        self.raw = lines.join("\r\n");
+    }
+
+    /// Parse the `Cache-Control` header, if present, into the directives relevant to
+    /// deciding whether and how long a response may be cached
+    pub fn get_cache_control(raw: &String) -> CacheControl {
+        let mut cc = CacheControl::default();
+
+        let reg = Regex::new(r"(?i)cache-control: *([^\r\n]*)\r\n").unwrap();
+        let capts = match reg.captures(raw.as_str()) {
+            Some(c) => c,
+            None => return cc,
+        };
+        let value = capts.get(1).unwrap().as_str().to_lowercase();
+
+        for directive in value.split(',') {
+            let directive = directive.trim();
+            if directive == "no-store" {
+                cc.no_store = true;
+            } else if directive == "no-cache" {
+                cc.no_cache = true;
+            } else if directive == "private" {
+                cc.private = true;
+            } else if let Some(secs) = directive.strip_prefix("max-age=") {
+                cc.max_age = secs.trim().parse::<u64>().ok();
+            }
+        }
+        cc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_control_parses_no_store() {
+        let raw = "GET / HTTP/1.1\r\nCache-Control: no-store\r\n\r\n".to_string();
+        let cc = Headers::get_cache_control(&raw);
+        assert!(cc.no_store);
+        assert!(!cc.no_cache);
+        assert!(!cc.private);
+        assert_eq!(cc.max_age, None);
+    }
+
+    #[test]
+    fn cache_control_parses_combined_directives() {
+        let raw = "GET / HTTP/1.1\r\nCache-Control: private, no-cache, max-age=60\r\n\r\n".to_string();
+        let cc = Headers::get_cache_control(&raw);
+        assert!(!cc.no_store);
+        assert!(cc.no_cache);
+        assert!(cc.private);
+        assert_eq!(cc.max_age, Some(60));
+    }
+
+    #[test]
+    fn cache_control_absent_is_all_defaults() {
+        let raw = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".to_string();
+        let cc = Headers::get_cache_control(&raw);
+        assert!(!cc.no_store);
+        assert!(!cc.no_cache);
+        assert!(!cc.private);
+        assert_eq!(cc.max_age, None);
+    }
+
+    #[test]
+    fn is_chunked_detects_transfer_encoding() {
+        let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_string();
+        assert!(Headers::is_chunked(&raw));
+    }
+
+    #[test]
+    fn is_chunked_false_when_content_length_used_instead() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\n".to_string();
+        assert!(!Headers::is_chunked(&raw));
+    }
 }
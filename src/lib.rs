@@ -25,16 +25,24 @@
 //! ```
 
 use std::{
-    io::{ErrorKind, Read, Result, Write},
-    net::{TcpListener, TcpStream},
+    io::{Error, ErrorKind, Read, Result, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
     str,
+    sync::Arc,
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 mod thread_pool;
 use thread_pool::ThreadPool;
 mod http;
-use http::{Http, Status};
+use http::{Headers, Http, Status};
+mod cache;
+use cache::{Cache, CacheEntry};
+mod config;
+#[cfg(feature = "https")]
+mod tls;
+#[cfg(feature = "https")]
+use tls::TlsStream;
 pub mod log;
 use log::{Log, LogLevel};
 mod prelude;
@@ -54,37 +62,68 @@ pub const THREADS: usize = 4;
 pub const LOG_LEVEL: LogLevel = LogLevel::Info;
 pub const PROXY_ADDRESS: &str = "127.0.0.1:3000";
 
+/// Upper bound on a proxied body's size, applied to both a `Content-Length`-declared
+/// body and the sum of `Transfer-Encoding: chunked` chunk sizes. Both values come
+/// straight off a header the other side controls, so without a cap a declared
+/// `Content-Length: 4000000000` (or a long run of inflated chunk sizes) forces a
+/// multi-gigabyte allocation per connection before a single byte is proxied
+pub const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// PROXY protocol version used to announce the original client address to the upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProto {
+    V1,
+}
+
 /// Structure for proxy server configuration
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Builder {
-    pub address: &'static str,
-    pub target: &'static str,
+    pub address: String,
+    pub target: String,
     pub log_level: LogLevel,
     pub threads: usize,
     pub chunk_size: usize,
+    pub routes: Vec<(String, String)>,
+    pub proxy_protocol: Option<ProxyProto>,
+    pub cache: Option<Arc<Cache>>,
+    #[cfg(feature = "https")]
+    pub tls_target: bool,
 }
 
 impl Builder {
     /// Create new proxy server builder
     pub fn new() -> Self {
         Self {
-            address: PROXY_ADDRESS,
-            target: TARGET_ADDRESS,
+            address: PROXY_ADDRESS.to_string(),
+            target: TARGET_ADDRESS.to_string(),
             log_level: LOG_LEVEL,
             threads: THREADS,
             chunk_size: CHUNK_SIZE,
+            routes: vec![],
+            proxy_protocol: None,
+            cache: None,
+            #[cfg(feature = "https")]
+            tls_target: false,
         }
     }
 
     /// Set proxy server address
-    pub fn with_address(mut self, address: &'static str) -> Self {
-        self.address = address;
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
         self
     }
 
-    /// Set proxy server target address
-    pub fn with_target(mut self, target: &'static str) -> Self {
-        self.target = target;
+    /// Set proxy server target address, used as a fallback when no route matches
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    /// Add a path prefix to upstream target mapping, e.g. `.with_route("/api", "127.0.0.1:3001")`.
+    /// Routes are matched by longest prefix; [`Builder::with_target`] is used as the fallback
+    /// when no route matches the request path.
+    pub fn with_route(mut self, path: impl Into<String>, target: impl Into<String>) -> Self {
+        self.routes.push((path.into(), target.into()));
         self
     }
 
@@ -100,6 +139,28 @@ impl Builder {
         self
     }
 
+    /// Emit a PROXY protocol header as the first bytes written to the upstream
+    /// connection, so the original client address survives behind the proxy
+    pub fn with_proxy_protocol(mut self, proxy_protocol: ProxyProto) -> Self {
+        self.proxy_protocol = Some(proxy_protocol);
+        self
+    }
+
+    /// Enable an in-memory LRU cache of upstream responses, bounded to `max_entries`.
+    /// Cacheability and freshness follow the response's `Cache-Control` header
+    pub fn with_cache(mut self, max_entries: usize) -> Self {
+        self.cache = Some(Arc::new(Cache::new(max_entries)));
+        self
+    }
+
+    /// Mark the configured target as an HTTPS backend, so `handle_proxy` connects to
+    /// it over TLS instead of a plain `TcpStream`
+    #[cfg(feature = "https")]
+    pub fn with_tls_target(mut self, tls_target: bool) -> Self {
+        self.tls_target = tls_target;
+        self
+    }
+
     /// Proxy server listener releasing [`std::net::TcpListener`] via thread pool
     pub fn bind(self) -> Result<()> {
         let listener = TcpListener::bind(&self.address)?;
@@ -109,7 +170,7 @@ impl Builder {
 
         let pool = ThreadPool::new(self.threads);
         for stream in listener.incoming() {
-            let cl = Handler::new(self);
+            let cl = Handler::new(self.clone());
             pool.execute(|| {
                 cl.handle_proxy(stream.expect("Error in incoming stream"))
                     .expect("Failed handle proxy");
@@ -117,6 +178,95 @@ impl Builder {
         }
         Ok(())
     }
+
+    /// Pick the upstream target for a request path by longest matching route prefix,
+    /// falling back to [`Builder::target`] when nothing matches.
+    ///
+    /// Matching is a plain `str::starts_with`, not segment-aware: a route registered
+    /// for `/api` also matches `/apikey/...`. Register routes with a trailing slash
+    /// (e.g. `/api/`) if that overlap would send traffic to the wrong backend.
+    fn resolve_target(&self, url: &str) -> &str {
+        let mut best: Option<&(String, String)> = None;
+        for route in &self.routes {
+            if url.starts_with(route.0.as_str()) {
+                let is_longer = match best {
+                    Some(b) => route.0.len() > b.0.len(),
+                    None => true,
+                };
+                if is_longer {
+                    best = Some(route);
+                }
+            }
+        }
+        match best {
+            Some(route) => route.1.as_str(),
+            None => self.target.as_str(),
+        }
+    }
+}
+
+/// Upstream connection: a plain TCP-backed [`Http`], or (with the `https` feature) a
+/// TLS-wrapped connection. Implementing [`Read`]/[`Write`] on the enum itself lets
+/// `handle_proxy` forward bytes through the same code path regardless of the variant.
+enum Upstream {
+    Plain(Http),
+    #[cfg(feature = "https")]
+    Tls(TlsStream),
+}
+
+impl Read for Upstream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            Upstream::Plain(h) => h.read(buf),
+            #[cfg(feature = "https")]
+            Upstream::Tls(t) => t.read(buf),
+        }
+    }
+}
+
+impl Write for Upstream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            Upstream::Plain(h) => h.write(buf),
+            #[cfg(feature = "https")]
+            Upstream::Tls(t) => t.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            Upstream::Plain(h) => h.flush(),
+            #[cfg(feature = "https")]
+            Upstream::Tls(t) => t.flush(),
+        }
+    }
+}
+
+/// Connect to `target`, going over TLS when [`Builder::with_tls_target`] is enabled.
+/// When `proxy_line` is given, it is written as the very first bytes on the raw
+/// connection — before the TLS handshake for a TLS target, so PROXY protocol and TLS
+/// don't fight over which goes first on the wire.
+#[cfg(feature = "https")]
+fn connect_upstream(config: &Builder, target: &str, proxy_line: Option<&[u8]>) -> Result<Upstream> {
+    if config.tls_target {
+        let hostname = target.split(':').next().unwrap_or(target);
+        return TlsStream::connect(target, hostname, proxy_line).map(Upstream::Tls);
+    }
+    let mut http = Http::connect(target)?;
+    if let Some(prefix) = proxy_line {
+        http.write(prefix)?;
+    }
+    Ok(Upstream::Plain(http))
+}
+
+/// Connect to `target` over a plain `TcpStream`, writing `proxy_line` first when given
+#[cfg(not(feature = "https"))]
+fn connect_upstream(_config: &Builder, target: &str, proxy_line: Option<&[u8]>) -> Result<Upstream> {
+    let mut http = Http::connect(target)?;
+    if let Some(prefix) = proxy_line {
+        http.write(prefix)?;
+    }
+    Ok(Upstream::Plain(http))
 }
 
 struct Handler {
@@ -132,6 +282,9 @@ impl Handler {
         let _log = Log::new(&self.config.log_level);
         _log.println(LogLevel::Info, "handle proxy", &client);
 
+        let peer_addr = client.peer_addr()?;
+        let local_addr = client.local_addr()?;
+
         let mut client = Http::from(client);
         let mut heads = vec![];
         client.read_to_end(&mut heads)?;
@@ -144,7 +297,32 @@ impl Handler {
         }
         let heads_n = heads_n.unwrap();
 
-        let http = Http::connect(&self.config.target);
+        let url = Headers::get_url(&heads_n);
+        let request_target = Headers::get_request_target(&heads_n);
+        let method = Headers::get_method(&heads_n);
+        let target = self.config.resolve_target(&url);
+
+        if let Some(cache) = &self.config.cache {
+            if method == "GET" {
+                if let Some(entry) = cache.get(&method, &request_target) {
+                    _log.println(LogLevel::Info, "cache hit", &request_target);
+                    client.write(entry.head.as_bytes())?;
+                    client.write(&entry.body)?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut req_headers = Headers::from_string(heads_n.clone());
+        req_headers.strip_hop_by_hop();
+        req_headers.append_forwarded_for(peer_addr.ip());
+        let heads_n = req_headers.raw;
+
+        let proxy_line = self
+            .config
+            .proxy_protocol
+            .map(|proto| proxy_protocol_header(proto, peer_addr, local_addr));
+        let http = connect_upstream(&self.config, target, proxy_line.as_deref().map(str::as_bytes));
         if let Err(e) = &http {
             _log.println(LogLevel::Warn, "Failed proxy", e);
             client.set_status(Status::BadGateway)?;
@@ -155,11 +333,32 @@ impl Handler {
         }
         let mut http = http?;
 
+        if let Some(len) = Headers::get_content_length(&heads_n) {
+            if len as usize > MAX_BODY_SIZE {
+                _log.println(LogLevel::Warn, "Request body too large", &len);
+                client.set_status(Status::BadRequest)?;
+                client.write("Content-Length: 0\r\n\r\n".as_bytes())?;
+                return Ok(());
+            }
+        }
+
         _log.println(LogLevel::Info, "write headers to target", &heads_n);
         http.write(heads_n.as_bytes())?;
-        let mut h = vec![];
-        http.read_to_end(&mut h)?;
+        if let Some(len) = Headers::get_content_length(&heads_n) {
+            if len > 0 {
+                let req_body = read_exact_body(&mut client, len as usize)?;
+                http.write(&req_body)?;
+            }
+        }
+
+        let h = read_headers_until_blank(&mut http)?;
+
+        let mut resp_headers = Headers::from_bytes(&h)?;
+        let resp_content_length = Headers::get_content_length(&resp_headers.raw);
+        let resp_chunked = Headers::is_chunked(&resp_headers.raw);
+        resp_headers.strip_hop_by_hop();
 
+        let h = resp_headers.raw.clone().into_bytes();
         _log.println(
             LogLevel::Info,
             "send headers to client",
@@ -167,29 +366,245 @@ impl Handler {
         );
         client.write(&h).expect("failed send headers");
 
-        loop {
-            let mut b = [0; CHUNK_SIZE];
-            let r_res = http.read(&mut b);
-            if let Err(e) = r_res {
+        // Chunked and EOF-framed bodies are forwarded to the client as they're read
+        // off `http`, rather than buffered in full first, so a long-lived chunked/SSE
+        // response streams through instead of waiting on the upstream to finish
+        let body = if resp_chunked {
+            read_chunked_body(&mut http, &mut client)?
+        } else if let Some(len) = resp_content_length {
+            let body = read_exact_body(&mut http, len as usize)?;
+            client.write(&body).expect("Failed write body");
+            body
+        } else {
+            stream_until_eof(&mut http, &mut client, CHUNK_SIZE, &_log)?
+        };
+
+        if let Some(cache) = &self.config.cache {
+            if method == "GET" {
+                let cc = Headers::get_cache_control(&resp_headers.raw);
+                let expires_at = cc.max_age.map(|secs| Instant::now() + Duration::from_secs(secs));
+                // `no-cache` has no revalidation path here (no conditional GET support),
+                // so it is treated the same as `no-store`: never cached, rather than
+                // stored as a dead entry that can never be served.
+                let cacheable = !cc.no_store && !cc.private && !cc.no_cache && expires_at.is_some();
+                if cacheable {
+                    cache.put(
+                        &method,
+                        &request_target,
+                        CacheEntry {
+                            head: resp_headers.raw,
+                            body,
+                            expires_at,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a PROXY protocol header line announcing the original client address to the
+/// upstream, derived from the accepted connection's peer and local socket addresses
+fn proxy_protocol_header(proto: ProxyProto, src: SocketAddr, dst: SocketAddr) -> String {
+    match proto {
+        ProxyProto::V1 => {
+            let family = match (src, dst) {
+                (SocketAddr::V4(_), SocketAddr::V4(_)) => Some("TCP4"),
+                (SocketAddr::V6(_), SocketAddr::V6(_)) => Some("TCP6"),
+                _ => None,
+            };
+            match family {
+                Some(family) => format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    family,
+                    src.ip(),
+                    dst.ip(),
+                    src.port(),
+                    dst.port()
+                ),
+                None => "PROXY UNKNOWN\r\n".to_string(),
+            }
+        }
+    }
+}
+
+/// Read a header block (status/request line and headers) up to and including the
+/// terminating blank line, without touching any bytes of the body that follows —
+/// unlike a full `read_to_end`, this doesn't block on a keep-alive connection waiting
+/// for the peer to close, and doesn't consume body bytes the framing logic still needs
+fn read_headers_until_blank<R: Read>(src: &mut R) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    let mut b = [0; 1];
+    loop {
+        let n = src.read(&mut b)?;
+        if n == 0 {
+            break;
+        }
+        buf.push(b[0]);
+        if buf.len() >= 4 && &buf[buf.len() - 4..] == b"\r\n\r\n" {
+            break;
+        }
+    }
+    Ok(buf)
+}
+
+/// Read exactly `len` bytes of a `Content-Length`-framed body, stopping early on EOF.
+/// Rejects `len` over [`MAX_BODY_SIZE`] before allocating, since `len` comes straight
+/// off a header the other side controls
+fn read_exact_body<R: Read>(src: &mut R, len: usize) -> Result<Vec<u8>> {
+    if len > MAX_BODY_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("body length {} exceeds the {} byte limit", len, MAX_BODY_SIZE),
+        ));
+    }
+    let mut body = vec![0; len];
+    let mut read = 0;
+    while read < len {
+        let n = src.read(&mut body[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    body.truncate(read);
+    Ok(body)
+}
+
+/// Read a single CRLF-terminated line, one byte at a time
+fn read_line<R: Read>(src: &mut R) -> Result<String> {
+    let mut line = vec![];
+    let mut b = [0; 1];
+    loop {
+        let n = src.read(&mut b)?;
+        if n == 0 || b[0] == b'\n' {
+            break;
+        }
+        if b[0] != b'\r' {
+            line.push(b[0]);
+        }
+    }
+    Ok(String::from_utf8_lossy(&line).to_string())
+}
+
+/// Decode a `Transfer-Encoding: chunked` body, reading `<hex-size>\r\n<data>\r\n`
+/// chunks until the terminating zero-length chunk, forwarding each chunk's bytes to
+/// `dst` as soon as it is decoded rather than buffering the whole body first — so a
+/// long-lived chunked/SSE response streams through as it arrives. Each chunk's
+/// declared size, and the running total, are checked against [`MAX_BODY_SIZE`] before
+/// being trusted, since both come from the upstream's chunk headers. The decoded bytes
+/// are also returned, for callers — like the cache — that need the whole body.
+fn read_chunked_body<R: Read, W: Write>(src: &mut R, dst: &mut W) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    loop {
+        let size_line = read_line(src)?;
+        let size = usize::from_str_radix(size_line.trim(), 16)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        if size == 0 {
+            read_line(src)?;
+            break;
+        }
+        if body.len() + size > MAX_BODY_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("chunked body exceeds the {} byte limit", MAX_BODY_SIZE),
+            ));
+        }
+
+        let chunk = read_exact_body(src, size)?;
+        dst.write(&chunk)?;
+        body.extend_from_slice(&chunk);
+        read_line(src)?;
+    }
+    Ok(body)
+}
+
+/// Stream a body with no declared framing until the upstream closes the connection,
+/// forwarding bytes to `dst` as they arrive
+fn stream_until_eof<R: Read, W: Write>(
+    src: &mut R,
+    dst: &mut W,
+    chunk_size: usize,
+    log: &Log,
+) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    loop {
+        let mut buf = vec![0; chunk_size];
+        let n = match src.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) => {
                 let log_l = match e.kind() {
                     ErrorKind::ConnectionReset => LogLevel::Info,
                     _ => LogLevel::Error,
                 };
-                _log.println(log_l, "Failed read chunk", e);
-            }
-            let mut buf = vec![];
-            b.map(|_b| {
-                if _b != 0 {
-                    buf.push(_b);
-                    return true;
-                }
-                false
-            });
-            if buf.len() == 0 {
-                break;
+                log.println(log_l, "Failed read chunk", e);
+                0
             }
-            client.write(&buf).expect("Failed write chunk");
+        };
+        if n == 0 {
+            break;
         }
-        Ok(())
+        dst.write(&buf[..n]).expect("Failed write chunk");
+        body.extend_from_slice(&buf[..n]);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod framing_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_exact_body_stops_at_declared_length() {
+        let mut src = Cursor::new(b"hello, world!".to_vec());
+        let body = read_exact_body(&mut src, 5).unwrap();
+        assert_eq!(body, b"hello");
+    }
+
+    #[test]
+    fn read_exact_body_truncates_on_early_eof() {
+        let mut src = Cursor::new(b"short".to_vec());
+        let body = read_exact_body(&mut src, 100).unwrap();
+        assert_eq!(body, b"short");
+    }
+
+    #[test]
+    fn read_chunked_body_decodes_multiple_chunks() {
+        let mut src = Cursor::new(b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n".to_vec());
+        let mut dst = Cursor::new(vec![]);
+        let body = read_chunked_body(&mut src, &mut dst).unwrap();
+        assert_eq!(body, b"Wikipedia");
+    }
+
+    #[test]
+    fn read_chunked_body_handles_single_empty_chunk() {
+        let mut src = Cursor::new(b"0\r\n\r\n".to_vec());
+        let mut dst = Cursor::new(vec![]);
+        let body = read_chunked_body(&mut src, &mut dst).unwrap();
+        assert_eq!(body, b"");
+    }
+
+    #[test]
+    fn read_chunked_body_forwards_each_chunk_to_dst_as_decoded() {
+        let mut src = Cursor::new(b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n".to_vec());
+        let mut dst = Cursor::new(vec![]);
+        read_chunked_body(&mut src, &mut dst).unwrap();
+        assert_eq!(dst.into_inner(), b"Wikipedia");
+    }
+
+    #[test]
+    fn read_chunked_body_rejects_chunk_over_size_limit() {
+        let size_line = format!("{:x}\r\n", MAX_BODY_SIZE + 1);
+        let mut src = Cursor::new(size_line.into_bytes());
+        let mut dst = Cursor::new(vec![]);
+        assert!(read_chunked_body(&mut src, &mut dst).is_err());
+    }
+
+    #[test]
+    fn read_exact_body_rejects_length_over_size_limit() {
+        let mut src = Cursor::new(vec![]);
+        assert!(read_exact_body(&mut src, MAX_BODY_SIZE + 1).is_err());
     }
 }
@@ -0,0 +1,56 @@
+///! TLS upstream connections, enabled by the `https` feature
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::{
+    io::{Error, ErrorKind, Read, Result, Write},
+    net::TcpStream,
+    sync::Arc,
+};
+
+/// A TLS-wrapped connection to an HTTPS upstream, used when
+/// [`crate::Builder::with_tls_target`] is enabled
+pub struct TlsStream(StreamOwned<ClientConnection, TcpStream>);
+
+impl TlsStream {
+    /// Connect to `target` and set up a TLS client session for `hostname`; the
+    /// handshake itself runs on the first read or write. When `proxy_line` is given
+    /// (PROXY protocol enabled), it is written to the raw `TcpStream` *before* the
+    /// handshake starts, so it lands as the first bytes on the wire rather than
+    /// inside the encrypted session.
+    pub fn connect(target: &str, hostname: &str, proxy_line: Option<&[u8]>) -> Result<Self> {
+        let mut tcp = TcpStream::connect(target)?;
+        if let Some(prefix) = proxy_line {
+            tcp.write_all(prefix)?;
+        }
+
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let server_name = hostname
+            .to_string()
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?;
+        let conn = ClientConnection::new(Arc::new(config), server_name)
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+        Ok(Self(StreamOwned::new(conn, tcp)))
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}